@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, StdError};
+use cosmwasm_std::{Addr, StdError, Uint128};
 use cw_controllers::{AdminError, HookError};
 use thiserror::Error;
 
@@ -13,14 +13,23 @@ pub enum ContractError {
     #[error("{0}")]
     Hook(#[from] HookError),
 
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from newer version {previous_version} to {new_version}")]
+    CannotMigrateVersion {
+        previous_version: String,
+        new_version: String,
+    },
+
     #[error("Unauthorized")]
     Unauthorized {},
 
-    #[error("Host-opponent pair already has a game going on")]
-    HostOpponentPairAlreadyHasGame {},
-
-    #[error("The host address has been blacklisted")]
-    HostAddressBlacklisted {},
+    #[error("{address} is banned: {reason}")]
+    AddressBanned { address: Addr, reason: String },
 
     #[error("No game found for the host opponent pair")]
     NoGameFoundForHostOpponentPair {
@@ -28,8 +37,147 @@ pub enum ContractError {
         opponent_address: Addr,
     },
 
-    #[error("Opponent played an invalid move")]
-    InvalidMove {
-        msg: String
+    #[error("No active game found with id {game_id}")]
+    GameNotFound { game_id: u64 },
+
+    #[error("No open challenge found with id {game_id}")]
+    ChallengeNotFound { game_id: u64 },
+
+    #[error("Cannot accept your own challenge")]
+    OwnChallenge {},
+
+    #[error("Already waiting in the matchmaking queue")]
+    AlreadyQueued {},
+
+    #[error("Not waiting in the matchmaking queue")]
+    NotQueued {},
+
+    #[error("No tournament found with id {tournament_id}")]
+    TournamentNotFound { tournament_id: u64 },
+
+    #[error("Maximum number of players must be between 2 and {max}")]
+    InvalidMaxPlayers { max: u32 },
+
+    #[error("Registration is closed")]
+    RegistrationClosed {},
+
+    #[error("Registration is still open")]
+    RegistrationOpen {},
+
+    #[error("Already registered")]
+    AlreadyRegistered {},
+
+    #[error("No league found with id {league_id}")]
+    LeagueNotFound { league_id: u64 },
+
+    #[error("League play deadline has not passed yet")]
+    PlayDeadlineNotReached {},
+
+    #[error("League play deadline must be after the registration deadline")]
+    InvalidPlayDeadline {},
+
+    #[error("Games that are part of a match or league cannot be cancelled")]
+    GameNotCancellable {},
+
+    #[error("No abort was proposed for this game")]
+    NoAbortProposed {},
+
+    #[error("Matches must be played over an odd number of rounds, at most {max}")]
+    InvalidBestOf { max: u32 },
+
+    #[error("Matches cannot be wagered")]
+    MatchWagerNotSupported {},
+
+    #[error("Public key must be a 33 or 65 byte secp256k1 key")]
+    InvalidPubkey {},
+
+    #[error("Public key cannot be changed during a game in the reveal phase")]
+    PubkeyLocked {},
+
+    #[error("{address} has not registered a public key")]
+    PubkeyNotRegistered { address: Addr },
+
+    #[error("Signature of {address} does not match the signed game state")]
+    InvalidSignature { address: Addr },
+
+    #[error("Signed game state must have a nonce above {nonce}")]
+    StaleGameState { nonce: u64 },
+
+    #[error("A signed game state is waiting to be settled")]
+    SettlementPending {},
+
+    #[error("No signed game state was submitted for game {game_id}")]
+    SettlementNotFound { game_id: u64 },
+
+    #[error("Dispute window has not closed yet")]
+    DisputeWindowOpen {},
+
+    #[error("Dispute window has closed")]
+    DisputeWindowClosed {},
+
+    #[error("Invalid move: {msg}")]
+    InvalidMove { msg: String },
+
+    #[error("Move commitment must be a hex encoded sha256 hash")]
+    InvalidCommitment {},
+
+    #[error("Revealed move and salt do not match the commitment")]
+    CommitmentMismatch {},
+
+    #[error("Move has already been played")]
+    MoveAlreadyPlayed {},
+
+    #[error("Game already has a result")]
+    GameAlreadyFinished {},
+
+    #[error("Game is not in the right phase for this action")]
+    InvalidGamePhase {},
+
+    #[error("Game deadline has passed")]
+    GameExpired {},
+
+    #[error("Game deadline has not passed yet")]
+    DeadlineNotReached {},
+
+    #[error("Move timeout must not be zero")]
+    InvalidMoveTimeout {},
+
+    #[error("Dispute window must not be zero")]
+    InvalidDisputeWindow {},
+
+    #[error("Fee must be at most 10000 basis points")]
+    InvalidFeeBps {},
+
+    #[error("Minimum wager must not be larger than the maximum wager")]
+    InvalidWagerLimits {},
+
+    #[error("Wager must be at least {min}")]
+    WagerBelowMinimum { min: Uint128 },
+
+    #[error("Wager must be at most {max}")]
+    WagerAboveMaximum { max: Uint128 },
+
+    #[error("{denom} is not accepted for wagers")]
+    DenomNotAccepted { denom: String },
+
+    #[error("CW20 token {address} is not accepted for wagers")]
+    Cw20NotWhitelisted { address: Addr },
+
+    #[error("Only a single coin can be wagered on a game")]
+    MultipleDenomsSent {},
+
+    #[error("Wager must be paid in {expected}, received {received}")]
+    WagerDenomMismatch { expected: String, received: String },
+
+    #[error("Wager must be {expected}, received {received}")]
+    WagerAmountMismatch {
+        expected: Uint128,
+        received: Uint128,
+    },
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
     }
 }
@@ -0,0 +1,98 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Env, Order, StdResult, Storage};
+use cw0::Expiration;
+use cw_storage_plus::Map;
+
+use crate::contract::{update_player_stats, DEFAULT_MOVE_TIMEOUT};
+use crate::error::ContractError;
+use crate::state::{
+    game_history, Ban, GameMove, GamePhase, GameResult, GameState, RuleSet, BLACKLIST, HOOKS,
+};
+
+// Storage layout of version 0.1, before moves were committed as hashes.
+// Each map has a key: (host_address, opponent_address) -> game_state
+const LEGACY_GAMES: Map<(Addr, Addr), LegacyGameState> = Map::new("games");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyGameState {
+    pub host_address: Addr,
+    pub opponent_address: Addr,
+    pub host_move: Option<GameMove>,
+    pub opponent_move: Option<GameMove>,
+    pub result: Option<GameResult>,
+}
+
+// Moves every 0.1 game into the history and returns the number of games migrated.
+// Finished games keep their moves and result, and are counted in the player stats.
+// Games still waiting for the opponent had the host move stored in plain text, so they
+// cannot be played on fairly and are closed as expired.
+// No wagers existed before 0.2, so there is nothing to refund.
+pub fn migrate_legacy_games(
+    storage: &mut dyn Storage,
+    env: &Env,
+    next_game_id: &mut u64,
+) -> StdResult<u64> {
+    // The active games now live under the same namespace, the old entries have to be read
+    // and removed before anything else is written there.
+    let legacy_games = LEGACY_GAMES
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (key, _) in legacy_games.iter() {
+        LEGACY_GAMES.remove(storage, key.clone());
+    }
+    for (_, legacy) in legacy_games.iter() {
+        *next_game_id += 1;
+        let phase = if legacy.result.is_some() {
+            GamePhase::Resolved
+        } else {
+            GamePhase::Expired
+        };
+        let game_state = GameState {
+            game_id: *next_game_id,
+            host_address: legacy.host_address.clone(),
+            opponent_address: legacy.opponent_address.clone(),
+            // Moves were not committed before 0.2.
+            host_commitment: None,
+            opponent_commitment: None,
+            host_move: legacy.host_move.clone(),
+            opponent_move: legacy.opponent_move.clone(),
+            phase,
+            result: legacy.result.clone(),
+            stake: None,
+            move_timeout: DEFAULT_MOVE_TIMEOUT,
+            deadline: Expiration::AtTime(env.block.time),
+            rule_set: RuleSet::Classic,
+            fee: None,
+            match_id: None,
+            league_id: None,
+            abort_proposed_by: None,
+        };
+        if game_state.result.is_some() {
+            update_player_stats(storage, &game_state)?;
+        }
+        game_history().save(storage, game_state.game_id, &game_state)?;
+    }
+    Ok(legacy_games.len() as u64)
+}
+
+// Hooks were used as the blacklist before 0.2. Now they are notified about games, so the
+// blacklisted addresses are moved to the blacklist and removed from the hooks.
+pub fn migrate_hook_blacklist(
+    storage: &mut dyn Storage,
+    hooks: Vec<String>,
+) -> Result<(), ContractError> {
+    for address in hooks {
+        // Hooks were validated when they were added.
+        let address = Addr::unchecked(address);
+        let ban = Ban {
+            reason: String::from("Blacklisted before 0.2"),
+            until: None,
+        };
+        BLACKLIST.save(storage, address.clone(), &ban)?;
+        HOOKS.remove_hook(storage, address)?;
+    }
+    Ok(())
+}
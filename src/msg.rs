@@ -1,21 +1,279 @@
-use crate::state::{GameMove, GameState};
-use cosmwasm_std::Addr;
+use crate::state::{
+    BracketMatch, Challenge, GameMove, GamePhase, GameResult, GameState, LeaguePoints, MatchState,
+    QueueEntry, Rating, RuleSet, SignedGameState, Stake, Standing, TieFeePolicy,
+};
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, StdResult, Timestamp, Uint128, WasmMsg,
+};
+use cw0::{Duration, Expiration};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     // While starting the game, the host does not need to specify an opponent.
+    // Every field is optional. The admin and fee collector default to the sender.
+    pub admin: Option<String>,
+    // One day by default.
+    pub move_timeout: Option<Duration>,
+    pub min_wager: Option<Uint128>,
+    pub max_wager: Option<Uint128>,
+    // Native denoms that can be wagered, any denom is accepted if empty.
+    #[serde(default)]
+    pub accepted_denoms: Vec<String>,
+    pub fee_bps: Option<u16>,
+    pub fee_collector: Option<String>,
+    // Ties are refunded in full by default.
+    pub tie_fee_policy: Option<TieFeePolicy>,
+    // 32 by default.
+    pub elo_k_factor: Option<u32>,
+    // One hour by default.
+    pub dispute_window: Option<Duration>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    StartGame { opponent: Addr, host_move: GameMove },
-    OpponentMove { host_address: String, opponent_address: String, opponent_move: GameMove },
-    UpdateAdmin { admin: Addr },
-    AddHook { hook_address: String },
-    RemoveHook { hook_address: String },
+    // Players commit to a move by sending `GameMove::commitment(player, salt)` instead of
+    // the move.
+    // Each player has `move_timeout` (one day by default) for their next move.
+    // Games use the Classic rule set unless the host picks another `rule_set`.
+    // Without an `opponent` the game is an open challenge, see AcceptChallenge.
+    StartGame {
+        opponent: Option<Addr>,
+        host_commitment: String,
+        move_timeout: Option<Duration>,
+        rule_set: Option<RuleSet>,
+    },
+    // The sender becomes the opponent of an open challenge, matching the host's wager.
+    // Both moves are committed then, so the game goes straight to the reveal phase.
+    AcceptChallenge {
+        game_id: u64,
+        opponent_commitment: String,
+    },
+    // Only the host can cancel a challenge nobody accepted yet, the wager is refunded.
+    CancelChallenge {
+        game_id: u64,
+    },
+    // Find a game against anyone with the same rule set (Classic by default) and the same
+    // wager, which is the sent funds. The sender is paired with the player that waited the
+    // longest, or waits in the queue until someone else joins. Paired games start with both
+    // moves committed, the player that waited is the host.
+    JoinQueue {
+        commitment: String,
+        rule_set: Option<RuleSet>,
+    },
+    // Leave the queue before being paired, the wager is refunded.
+    LeaveQueue {},
+    // The host can cancel a game before the opponent committed, the wager is refunded.
+    CancelGame {
+        game_id: u64,
+    },
+    // Either player of an unfinished game can propose to abort it until a move is revealed.
+    // Once the other player accepts, the game is cancelled and both wagers are refunded.
+    ProposeAbort {
+        game_id: u64,
+    },
+    AcceptAbort {
+        game_id: u64,
+    },
+    // Best-of-N series against the opponent, `best_of` must be odd. Every round is a game of
+    // its own that both players commit to with CommitMove and reveal as usual. Tied rounds
+    // are replayed, the first player to win the majority of the rounds wins the match.
+    StartMatch {
+        opponent: Addr,
+        best_of: u32,
+        move_timeout: Option<Duration>,
+        rule_set: Option<RuleSet>,
+    },
+    // Single-elimination tournament. Every bracket match is a best-of-N match, winners
+    // advance until the champion wins the entry fees, minus the house fee.
+    CreateTournament {
+        entry_fee: Option<Coin>,
+        max_players: u32,
+        registration_deadline: Expiration,
+        best_of: u32,
+        rule_set: Option<RuleSet>,
+    },
+    // The sent funds have to match the entry fee. The bracket is seeded as soon as the
+    // tournament is full.
+    RegisterForTournament {
+        tournament_id: u64,
+    },
+    // Anyone can seed the bracket once the registration deadline passed. The tournament is
+    // cancelled and the entry fees refunded if fewer than two players registered.
+    StartTournament {
+        tournament_id: u64,
+    },
+    // Anyone can close the games of the current round that are past their deadline, so a
+    // round where both players went silent cannot hold up the tournament. Whoever did not
+    // move in time forfeits the match, the higher seed advances if neither moved.
+    ExpireTournamentRound {
+        tournament_id: u64,
+    },
+    // Round-robin league, every player plays one game against every other player. Wins,
+    // ties and losses are worth 3, 1 and 0 points unless other `points` are given.
+    CreateLeague {
+        max_players: u32,
+        registration_deadline: Expiration,
+        play_deadline: Expiration,
+        points: Option<LeaguePoints>,
+        rule_set: Option<RuleSet>,
+    },
+    // The fixtures are created as soon as the league is full.
+    RegisterForLeague {
+        league_id: u64,
+    },
+    // Anyone can create the fixtures once the registration deadline passed. The league is
+    // cancelled if fewer than two players registered.
+    StartLeague {
+        league_id: u64,
+    },
+    // Anyone can close the league once the play deadline passed. Unfinished fixtures are
+    // forfeited by whoever did not move in time, fixtures still in their reveal window are
+    // left to be revealed.
+    CloseLeague {
+        league_id: u64,
+    },
+    // Commit to a move in a game by id, for either player. This is how both players commit
+    // to the rounds of a match, for the opponent it works the same as OpponentMoveById.
+    CommitMove {
+        game_id: u64,
+        commitment: String,
+    },
+    // Register the secp256k1 public key the sender signs off-chain game states with. The key
+    // cannot change while the sender has a game in the reveal phase.
+    RegisterPubkey {
+        pubkey: Binary,
+    },
+    // Players can play a game off-chain once both committed on-chain, so both wagers are in
+    // escrow. Either player submits the last state both of them signed. Until the dispute
+    // window closes, either player can replace it with a state with a higher nonce.
+    SettleSignedGame {
+        state: SignedGameState,
+        host_signature: Binary,
+        opponent_signature: Binary,
+    },
+    // Anyone can pay out the submitted state once the dispute window closed.
+    FinalizeSettlement {
+        game_id: u64,
+    },
+    // Only the opponent of the game, or one of their delegates, can play the opponent move.
+    // The opponent is the sender, unless a delegate plays `on_behalf_of` the opponent.
+    OpponentMove {
+        host_address: String,
+        opponent_commitment: String,
+        on_behalf_of: Option<String>,
+    },
+    // Same as OpponentMove, for a game picked by id instead of by its players.
+    OpponentMoveById {
+        game_id: u64,
+        opponent_commitment: String,
+    },
+    // Allow another address to play and reveal the opponent move in games against the sender.
+    AddDelegate {
+        delegate: String,
+    },
+    RemoveDelegate {
+        delegate: String,
+    },
+    // Reveal the committed move once both players committed.
+    // The game is resolved once both moves are revealed.
+    RevealMove {
+        host_address: String,
+        opponent_address: String,
+        game_move: GameMove,
+        salt: String,
+    },
+    RevealMoveById {
+        game_id: u64,
+        game_move: GameMove,
+        salt: String,
+    },
+    // Close a game after its deadline. The player that did not move in time forfeits.
+    ClaimTimeout {
+        host_address: String,
+        opponent_address: String,
+    },
+    ClaimTimeoutById {
+        game_id: u64,
+    },
+    // Start or join a game with a CW20 wager. The embedded message is a `ReceiveMsg`.
+    Receive(Cw20ReceiveMsg),
+    UpdateCw20Whitelist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    // Admin only. Fields that are left out keep their current value.
+    UpdateConfig(ConfigUpdate),
+    UpdateAdmin {
+        admin: Addr,
+    },
+    // Admin only. Banned players cannot start games or play the opponent move until
+    // `until`, or until they are unbanned.
+    Ban {
+        address: String,
+        reason: String,
+        until: Option<Timestamp>,
+    },
+    Unban {
+        address: String,
+    },
+    AddHook {
+        hook_address: String,
+    },
+    RemoveHook {
+        hook_address: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ConfigUpdate {
+    pub move_timeout: Option<Duration>,
+    pub min_wager: Option<Uint128>,
+    pub max_wager: Option<Uint128>,
+    // Lifts the maximum wager, `max_wager` is ignored then.
+    #[serde(default)]
+    pub remove_max_wager: bool,
+    pub accepted_denoms: Option<Vec<String>>,
+    pub fee_bps: Option<u16>,
+    pub fee_collector: Option<String>,
+    pub tie_fee_policy: Option<TieFeePolicy>,
+    pub elo_k_factor: Option<u32>,
+    pub dispute_window: Option<Duration>,
+}
+
+// Messages embedded in a CW20 `Send`, the sent tokens are the wager.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    StartGame {
+        opponent: Option<Addr>,
+        host_commitment: String,
+        move_timeout: Option<Duration>,
+        rule_set: Option<RuleSet>,
+    },
+    OpponentMove {
+        host_address: String,
+        opponent_commitment: String,
+        on_behalf_of: Option<String>,
+    },
+    OpponentMoveById {
+        game_id: u64,
+        opponent_commitment: String,
+    },
+    AcceptChallenge {
+        game_id: u64,
+        opponent_commitment: String,
+    },
+    JoinQueue {
+        commitment: String,
+        rule_set: Option<RuleSet>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -25,14 +283,236 @@ pub enum QueryMsg {
         host_address: Addr,
         opponent_address: Addr,
     },
+    // The list queries return a GamesResponse ordered by game id. Pass the returned
+    // next_start_after back as start_after to get the next page.
     GetGameByHost {
         host_address: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
     GetGameByOpponent {
         opponent_address: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // Active games that are waiting for commitments or for reveals.
+    GetGamesByPhase {
+        phase: GamePhase,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // All active games.
+    AllGames {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // Open challenges ordered by game id, optionally only those with exactly this wager
+    // (no wager if the amount is zero) or this rule set.
+    ListOpenChallenges {
+        stake: Option<Stake>,
+        rule_set: Option<RuleSet>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // Whether the player is waiting in the matchmaking queue, and how many players of their
+    // tier are ahead of them.
+    QueueStatus {
+        player: Addr,
+    },
+
+    // Looks up active and finished games. Finished games are moved to the history.
+    GetGameById {
+        game_id: u64,
+    },
+    // Finished games of the pair, paged like the other game lists.
+    GetGameHistory {
+        host_address: Addr,
+        opponent_address: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
 
+    // Addresses allowed to move for the player.
+    GetDelegates {
+        player: Addr,
+    },
+    // Players that are currently banned, ordered by address.
+    ListBanned {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Wins, losses and wagered volume of a player over all finished games.
+    GetPlayerStats {
+        address: Addr,
+    },
+    // Rated players ordered from the highest rating down. Pass the returned
+    // next_start_after back as start_after to get the next page.
+    Leaderboard {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // The match with all of its rounds so far, the last round is the current one.
+    GetMatch {
+        match_id: u64,
+    },
+    // League table ordered by points, then wins, then fewest forfeits, then registration order.
+    LeagueStandings {
+        league_id: u64,
+    },
+    // The tournament with its whole bracket.
+    GetTournament {
+        tournament_id: u64,
+    },
+    // Bracket matches of one round, counting from 1, or of the current round.
+    GetTournamentRound {
+        tournament_id: u64,
+        round: Option<u32>,
+    },
+    // Signed game state waiting for its dispute window to close, if any.
+    GetSettlement {
+        game_id: u64,
+    },
+    // Public key the player signs off-chain game states with, if registered.
+    GetPubkey {
+        address: Addr,
+    },
     GetAdmin {},
+    GetConfig {},
+    GetCw20Whitelist {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GamesResponse {
+    pub games: Vec<GameState>,
+    // Id of the last game in this page, None once there are no more games.
+    pub next_start_after: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChallengesResponse {
+    pub challenges: Vec<Challenge>,
+    // Id of the last challenge in this page, None once there are no more challenges.
+    pub next_start_after: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueueStatusResponse {
+    // None if the player is not waiting.
+    pub entry: Option<QueueEntry>,
+    pub tier: Option<String>,
+    pub players_ahead: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BannedAddress {
+    pub address: Addr,
+    pub reason: String,
+    pub until: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BannedResponse {
+    pub banned: Vec<BannedAddress>,
+    // Last address in this page, None once there are no more bans.
+    pub next_start_after: Option<String>,
+}
+
+// Sent to every registered hook contract as `{"game_hook": {...}}`, so other contracts can
+// react to games without polling.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameHookMsg {
+    GameStarted {
+        game_id: u64,
+        host: Addr,
+        opponent: Addr,
+        stake: Option<Stake>,
+    },
+    // A player committed to their move, the game moves to the reveal phase once both did.
+    MoveMade {
+        game_id: u64,
+        player: Addr,
+    },
+    // A player proposed to abort the game, it is cancelled once the other player accepts.
+    AbortProposed {
+        game_id: u64,
+        player: Addr,
+    },
+    // The game is finished, either resolved, expired or cancelled. An expired game has no
+    // result when neither player revealed, a cancelled game never has one.
+    GameResolved {
+        game_id: u64,
+        host: Addr,
+        opponent: Addr,
+        phase: GamePhase,
+        result: Option<GameResult>,
+    },
+}
+
+impl GameHookMsg {
+    pub fn into_binary(self) -> StdResult<Binary> {
+        to_binary(&GameHookExecuteMsg::GameHook(self))
+    }
+
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg: self.into_binary()?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+// Wraps GameHookMsg so hook contracts can add it to their own ExecuteMsg.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum GameHookExecuteMsg {
+    GameHook(GameHookMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub ratings: Vec<Rating>,
+    // Last player in this page, None once there are no more players.
+    pub next_start_after: Option<String>,
+}
+
+// Set as the data of the StartGame response.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StartGameResponse {
+    pub game_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchResponse {
+    // Includes the running score of both players.
+    pub match_state: MatchState,
+    pub rounds: Vec<GameState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeagueStandingsResponse {
+    pub standings: Vec<Standing>,
+    // Fixtures that have a result.
+    pub finished_fixtures: u32,
+    pub total_fixtures: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TournamentRoundResponse {
+    pub round: u32,
+    pub matches: Vec<BracketMatch>,
+}
+
+// Set as the data of the StartMatch response.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StartMatchResponse {
+    pub match_id: u64,
+    // First round of the match.
+    pub game_id: u64,
 }
 
 // #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
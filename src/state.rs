@@ -1,9 +1,17 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use cosmwasm_std::Addr;
-use cw_storage_plus::{Item, Map};
+use std::fmt;
+
+use cosmwasm_std::{
+    to_binary, to_vec, Addr, BankMsg, Binary, BlockInfo, Coin, CosmosMsg, Empty, StdResult,
+    Timestamp, Uint128, WasmMsg,
+};
+use cw0::{Duration, Expiration};
+use cw20::{Cw20ExecuteMsg, Denom};
 use cw_controllers::{Admin, Hooks};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -11,21 +19,530 @@ pub struct State {
 }
 
 pub const STATE: Item<State> = Item::new("state");
-// Each map has a key: (host_address, opponent_address) -> game_state
-pub const GAMES: Map<(Addr, Addr), GameState> = Map::new("games");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    // Time each player has for their next move, unless the host picks another one.
+    pub move_timeout: Duration,
+    // Limits for wagered games, games without a wager are always allowed.
+    pub min_wager: Uint128,
+    pub max_wager: Option<Uint128>,
+    // Native denoms that can be wagered, any denom is accepted if empty.
+    // CW20 tokens are accepted through the CW20 whitelist instead.
+    pub accepted_denoms: Vec<String>,
+    // House fee taken from wagers, in basis points, and the address it is paid to.
+    pub fee_bps: u16,
+    pub fee_collector: Addr,
+    pub tie_fee_policy: TieFeePolicy,
+    // How much a single game can change an Elo rating.
+    pub elo_k_factor: u32,
+    // Time to dispute a signed game state with a later one before it is paid out.
+    pub dispute_window: Duration,
+}
+
+// Whether the house fee is taken when a wagered game ends in a tie.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TieFeePolicy {
+    // Both players get their full wager back.
+    Refund,
+    // The fee is taken from both wagers, the rest is refunded.
+    ChargeFee,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+// CW20 token contracts that are accepted for wagers, maintained by the admin.
+pub const CW20_WHITELIST: Map<Addr, Empty> = Map::new("cw20_whitelist");
+// Addresses a player allowed to make moves for them: (player, delegate) -> Empty
+pub const DELEGATES: Map<(Addr, Addr), Empty> = Map::new("delegates");
+// Last id handed out to a game, ids increase monotonically.
+pub const GAME_COUNTER: Item<u64> = Item::new("game_counter");
+// Best-of-N series of games: match_id -> match_state
+pub const MATCHES: Map<u64, MatchState> = Map::new("matches");
+pub const MATCH_COUNTER: Item<u64> = Item::new("match_counter");
+// Single-elimination tournaments: tournament_id -> tournament
+pub const TOURNAMENTS: Map<u64, Tournament> = Map::new("tournaments");
+pub const TOURNAMENT_COUNTER: Item<u64> = Item::new("tournament_counter");
+// Round-robin leagues: league_id -> league
+pub const LEAGUES: Map<u64, League> = Map::new("leagues");
+pub const LEAGUE_COUNTER: Item<u64> = Item::new("league_counter");
+// Games without an opponent, waiting for anyone to accept them: game_id -> challenge
+pub const OPEN_CHALLENGES: Map<u64, Challenge> = Map::new("open_challenges");
+// Players waiting to be paired, in the order they joined: (tier, sequence) -> entry
+pub const QUEUE: Map<(String, u64), QueueEntry> = Map::new("queue");
+// Where each waiting player is in the queue: address -> (tier, sequence)
+pub const QUEUED_PLAYERS: Map<Addr, (String, u64)> = Map::new("queued_players");
+pub const QUEUE_COUNTER: Item<u64> = Item::new("queue_counter");
+// secp256k1 keys players sign off-chain game states with: address -> pubkey
+pub const PUBKEYS: Map<Addr, Binary> = Map::new("pubkeys");
+// Signed game states waiting for their dispute window to close: game_id -> settlement
+pub const SETTLEMENTS: Map<u64, Settlement> = Map::new("settlements");
+// Results of all finished games per player: address -> stats
+pub const PLAYER_STATS: Map<Addr, PlayerStats> = Map::new("player_stats");
+// Players banned by the admin: address -> ban
+pub const BLACKLIST: Map<Addr, Ban> = Map::new("blacklist");
 pub const ADMIN: Admin = Admin::new("admin");
 pub const HOOKS: Hooks = Hooks::new("hooks");
 
+pub struct GameIndexes<'a> {
+    // host_address -> game ids
+    pub host: MultiIndex<'a, Addr, GameState, u64>,
+    // opponent_address -> game ids
+    pub opponent: MultiIndex<'a, Addr, GameState, u64>,
+    // (host_address, opponent_address) -> game ids
+    pub pair: MultiIndex<'a, (Addr, Addr), GameState, u64>,
+    // phase -> game ids
+    pub phase: MultiIndex<'a, String, GameState, u64>,
+}
+
+impl<'a> IndexList<GameState> for GameIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<GameState>> + '_> {
+        let v: Vec<&dyn Index<GameState>> =
+            vec![&self.host, &self.opponent, &self.pair, &self.phase];
+        Box::new(v.into_iter())
+    }
+}
+
+// Active games: game_id -> game_state
+// Indexed by host, opponent, pair and phase, so none of the lookups has to scan all games.
+pub fn games<'a>() -> IndexedMap<'a, u64, GameState, GameIndexes<'a>> {
+    let indexes = GameIndexes {
+        host: MultiIndex::new(|game| game.host_address.clone(), "games", "games__host"),
+        opponent: MultiIndex::new(
+            |game| game.opponent_address.clone(),
+            "games",
+            "games__opponent",
+        ),
+        pair: MultiIndex::new(
+            |game| (game.host_address.clone(), game.opponent_address.clone()),
+            "games",
+            "games__pair",
+        ),
+        phase: MultiIndex::new(
+            |game| game.phase.as_str().to_string(),
+            "games",
+            "games__phase",
+        ),
+    };
+    IndexedMap::new("games", indexes)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Rating {
+    pub player: Addr,
+    pub rating: u32,
+    // Rated games played, only games where both players committed to a move are rated.
+    pub games: u64,
+}
+
+pub struct RatingIndexes<'a> {
+    // rating -> players
+    pub rating: MultiIndex<'a, u32, Rating, Addr>,
+}
+
+impl<'a> IndexList<Rating> for RatingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Rating>> + '_> {
+        let v: Vec<&dyn Index<Rating>> = vec![&self.rating];
+        Box::new(v.into_iter())
+    }
+}
+
+// Elo rating of every player that finished a rated game: address -> rating
+pub fn ratings<'a>() -> IndexedMap<'a, Addr, Rating, RatingIndexes<'a>> {
+    let indexes = RatingIndexes {
+        rating: MultiIndex::new(|rating| rating.rating, "ratings", "ratings__rating"),
+    };
+    IndexedMap::new("ratings", indexes)
+}
+
+pub struct HistoryIndexes<'a> {
+    // (host_address, opponent_address) -> game ids
+    pub pair: MultiIndex<'a, (Addr, Addr), GameState, u64>,
+}
+
+impl<'a> IndexList<GameState> for HistoryIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<GameState>> + '_> {
+        let v: Vec<&dyn Index<GameState>> = vec![&self.pair];
+        Box::new(v.into_iter())
+    }
+}
+
+// Finished games are moved out of the active games into the history, so the pair can play again.
+// game_id -> game_state
+pub fn game_history<'a>() -> IndexedMap<'a, u64, GameState, HistoryIndexes<'a>> {
+    let indexes = HistoryIndexes {
+        pair: MultiIndex::new(
+            |game| (game.host_address.clone(), game.opponent_address.clone()),
+            "game_history",
+            "game_history__pair",
+        ),
+    };
+    IndexedMap::new("game_history", indexes)
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct GameState {
-    // When the game starts, host and opponent addresses will definitely be there. 
-    // Host move is Option just for uniformity.
+    pub game_id: u64,
+    // When the game starts, host and opponent addresses will definitely be there.
     pub host_address: Addr,
     pub opponent_address: Addr,
+    // Both players only publish a hash of their move and a secret salt.
+    // The moves themselves are stored once revealed, after both sides committed.
+    // The host commits when starting a game, but only after the round started in a match.
+    pub host_commitment: Option<String>,
+    pub opponent_commitment: Option<String>,
     pub host_move: Option<GameMove>,
     pub opponent_move: Option<GameMove>,
+    pub phase: GamePhase,
     pub result: Option<GameResult>,
+    // Tokens wagered by each player, escrowed by the contract until the game is resolved.
+    pub stake: Option<Stake>,
+    // Time each player has for their next move. The deadline is reset when the game
+    // moves to the reveal phase.
+    pub move_timeout: Duration,
+    pub deadline: Expiration,
+    // Moves that can be played and which move beats which.
+    pub rule_set: RuleSet,
+    // House fee taken from the pot, set once a wagered game is settled.
+    pub fee: Option<Uint128>,
+    // Match this game is a round of, if any.
+    pub match_id: Option<u64>,
+    // League this game is a fixture of, if any.
+    pub league_id: Option<u64>,
+    // Player that proposed to abort the game, the other player has to accept.
+    pub abort_proposed_by: Option<Addr>,
+}
+
+// A game the host started without an opponent. It becomes a regular game with the same
+// id once accepted, the host's wager stays in escrow until then.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Challenge {
+    pub game_id: u64,
+    pub host_address: Addr,
+    pub host_commitment: String,
+    pub stake: Option<Stake>,
+    pub move_timeout: Duration,
+    pub rule_set: RuleSet,
+}
+
+// Moves both players agreed on off-chain. Each new state has a higher nonce, so a later
+// state replaces an earlier one during the dispute window.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedGameState {
+    pub game_id: u64,
+    pub nonce: u64,
+    // The player with more round wins wins the game, equal wins are a tie.
+    pub rounds: Vec<SignedRound>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedRound {
+    pub host_move: GameMove,
+    pub opponent_move: GameMove,
+}
+
+impl SignedGameState {
+    /// Bytes both players sign: the contract address, a colon and the JSON encoded state.
+    /// The signatures are checked against the sha256 hash of these bytes.
+    pub fn sign_bytes(&self, contract: &Addr) -> StdResult<Vec<u8>> {
+        let mut bytes = format!("{}:", contract).into_bytes();
+        bytes.extend(to_vec(self)?);
+        Ok(bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Settlement {
+    pub state: SignedGameState,
+    pub submitted_by: Addr,
+    // Until then either player can submit a state with a higher nonce.
+    pub dispute_deadline: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Tournament {
+    pub tournament_id: u64,
+    pub creator: Addr,
+    // Paid by every player when registering, the champion wins all of it minus the house fee.
+    pub entry_fee: Option<Stake>,
+    pub max_players: u32,
+    pub registration_deadline: Expiration,
+    // Format of every bracket match.
+    pub best_of: u32,
+    pub rule_set: RuleSet,
+    pub move_timeout: Duration,
+    // Registered players in registration order, which is also their seed.
+    pub players: Vec<Addr>,
+    pub phase: TournamentPhase,
+    // Bracket matches of every round so far, the last round is the current one.
+    pub rounds: Vec<Vec<BracketMatch>>,
+    pub champion: Option<Addr>,
+    // House fee taken from the prize pool, set once the champion is paid.
+    pub fee: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BracketMatch {
+    // The higher seed. Without an opponent the host gets a bye into the next round.
+    pub host: Addr,
+    pub opponent: Option<Addr>,
+    pub match_id: Option<u64>,
+    pub winner: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum TournamentPhase {
+    // Players can register until the deadline, or until the tournament is full.
+    Registration,
+    // The bracket is seeded and matches are being played.
+    InProgress,
+    // The champion was paid.
+    Finished,
+    // Not enough players registered, every entry fee was refunded.
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct League {
+    pub league_id: u64,
+    pub creator: Addr,
+    pub max_players: u32,
+    pub registration_deadline: Expiration,
+    // Fixtures have to be finished by then, whoever has not moved by then forfeits.
+    pub play_deadline: Expiration,
+    pub points: LeaguePoints,
+    pub rule_set: RuleSet,
+    pub move_timeout: Duration,
+    // Registered players in registration order, the last tiebreaker.
+    pub players: Vec<Addr>,
+    pub phase: LeaguePhase,
+    // Game ids of the fixtures, one game for every pair of players.
+    pub fixtures: Vec<u64>,
+    // One entry per player, in registration order.
+    pub standings: Vec<Standing>,
+}
+
+// Points a player gets for the result of a fixture. Forfeits count as losses.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaguePoints {
+    pub win: u32,
+    pub tie: u32,
+    pub loss: u32,
+}
+
+impl Default for LeaguePoints {
+    fn default() -> Self {
+        LeaguePoints {
+            win: 3,
+            tie: 1,
+            loss: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Standing {
+    pub player: Addr,
+    pub played: u32,
+    pub wins: u32,
+    pub ties: u32,
+    pub losses: u32,
+    pub forfeits: u32,
+    pub points: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum LeaguePhase {
+    // Players can register until the deadline, or until the league is full.
+    Registration,
+    // Fixtures are being played.
+    InProgress,
+    // Every fixture has a result.
+    Finished,
+    // Fewer than two players registered.
+    Cancelled,
+}
+
+// A player waiting in the matchmaking queue, their wager stays in escrow until they are
+// paired or leave the queue.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueueEntry {
+    pub player: Addr,
+    pub commitment: String,
+    pub stake: Option<Stake>,
+    pub rule_set: RuleSet,
+}
+
+impl QueueEntry {
+    // Players are only paired with someone playing the same rules for the same wager.
+    pub fn tier(&self) -> String {
+        let rule_set = format!("{:?}", self.rule_set);
+        match &self.stake {
+            Some(Stake {
+                denom: Denom::Native(denom),
+                amount,
+            }) => format!("{}/native/{}/{}", rule_set, denom, amount),
+            Some(Stake {
+                denom: Denom::Cw20(address),
+                amount,
+            }) => format!("{}/cw20/{}/{}", rule_set, address, amount),
+            None => format!("{}/free", rule_set),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchState {
+    pub match_id: u64,
+    pub host_address: Addr,
+    pub opponent_address: Addr,
+    // Odd number of rounds, the first player to win the majority of them wins the match.
+    pub best_of: u32,
+    // Rounds won by each player. Tied rounds are replayed and do not count.
+    pub host_score: u32,
+    pub opponent_score: u32,
+    // Game ids of the rounds played so far, the last one is the current round.
+    pub rounds: Vec<u64>,
+    pub phase: MatchPhase,
+    pub result: Option<GameResult>,
+    // Every round is played with the same rules and timeout.
+    pub rule_set: RuleSet,
+    pub move_timeout: Duration,
+    // Tournament this match is part of, if any.
+    pub tournament_id: Option<u64>,
+}
+
+impl MatchState {
+    pub fn wins_needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum MatchPhase {
+    // Rounds are being played.
+    InProgress,
+    // One player won the majority of the rounds.
+    Resolved,
+    // A round was not finished before its deadline, whoever was late forfeits the match.
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PlayerStats {
+    pub wins: u64,
+    pub losses: u64,
+    pub ties: u64,
+    // Games the player did not finish before the deadline, these also count as losses
+    // when the other player did their part.
+    pub forfeits: u64,
+    // Wins in a row, reset by a loss, tie or forfeit.
+    pub current_streak: u64,
+    pub best_streak: u64,
+    // Total amount the player wagered, per token.
+    pub volume: Vec<Stake>,
+}
+
+impl PlayerStats {
+    pub fn add_volume(&mut self, stake: &Stake) {
+        match self
+            .volume
+            .iter_mut()
+            .find(|volume| volume.denom == stake.denom)
+        {
+            Some(volume) => volume.amount += stake.amount,
+            None => self.volume.push(stake.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ban {
+    pub reason: String,
+    // The ban is lifted automatically at this block time, it is permanent without one.
+    pub until: Option<Timestamp>,
+}
+
+impl Ban {
+    pub fn is_active(&self, block: &BlockInfo) -> bool {
+        match self.until {
+            Some(until) => block.time < until,
+            None => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Stake {
+    // Either a native denom or a whitelisted CW20 token contract.
+    pub denom: Denom,
+    pub amount: Uint128,
+}
+
+impl Stake {
+    pub fn denom_name(&self) -> String {
+        match &self.denom {
+            Denom::Native(denom) => denom.clone(),
+            Denom::Cw20(address) => address.to_string(),
+        }
+    }
+
+    // Message paying out `amount` of the staked token from the escrow.
+    pub fn send(&self, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = match &self.denom {
+            Denom::Native(denom) => BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount,
+                }],
+            }
+            .into(),
+            Denom::Cw20(address) => WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        };
+        Ok(msg)
+    }
+}
+
+impl fmt::Display for Stake {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.amount, self.denom_name())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum GamePhase {
+    // Waiting for the players to commit to their moves.
+    Committing,
+    // Both moves are committed, waiting for both players to reveal.
+    Revealing,
+    // Both moves are revealed and the result is known.
+    Resolved,
+    // The game was not finished before the deadline.
+    Expired,
+    // The host cancelled the game before the opponent moved, or both players agreed to
+    // abort it. Every wager was refunded.
+    Cancelled,
+}
+
+impl GamePhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GamePhase::Committing => "Committing",
+            GamePhase::Revealing => "Revealing",
+            GamePhase::Resolved => "Resolved",
+            GamePhase::Expired => "Expired",
+            GamePhase::Cancelled => "Cancelled",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -33,6 +550,74 @@ pub enum GameMove {
     Rock,
     Paper,
     Scissors,
+    // Only part of the Rpsls rule set.
+    Lizard,
+    Spock,
+}
+
+impl GameMove {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameMove::Rock => "Rock",
+            GameMove::Paper => "Paper",
+            GameMove::Scissors => "Scissors",
+            GameMove::Lizard => "Lizard",
+            GameMove::Spock => "Spock",
+        }
+    }
+
+    // Moves this move wins against, in the largest rule set. Smaller rule sets only use the
+    // part of this relation between their own moves.
+    fn beaten_moves(&self) -> &'static [GameMove] {
+        match self {
+            GameMove::Rock => &[GameMove::Scissors, GameMove::Lizard],
+            GameMove::Paper => &[GameMove::Rock, GameMove::Spock],
+            GameMove::Scissors => &[GameMove::Paper, GameMove::Lizard],
+            GameMove::Lizard => &[GameMove::Spock, GameMove::Paper],
+            GameMove::Spock => &[GameMove::Scissors, GameMove::Rock],
+        }
+    }
+
+    /// Hex encoded sha256 hash of `"<player>:<move>:<salt>"`, e.g.
+    /// `sha256("terra1...:Rock:my secret salt")`. This is what players submit instead of their
+    /// move and later reveal. The player address keeps the other side from copying a
+    /// commitment and revealing the same move.
+    pub fn commitment(&self, player: &Addr, salt: &str) -> String {
+        let preimage = format!("{}:{}:{}", player, self.as_str(), salt);
+        hex::encode(Sha256::digest(preimage.as_bytes()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum RuleSet {
+    // Rock, paper, scissors.
+    Classic,
+    // Rock, paper, scissors, lizard, Spock.
+    Rpsls,
+}
+
+impl RuleSet {
+    pub fn moves(&self) -> &'static [GameMove] {
+        match self {
+            RuleSet::Classic => &[GameMove::Rock, GameMove::Paper, GameMove::Scissors],
+            RuleSet::Rpsls => &[
+                GameMove::Rock,
+                GameMove::Paper,
+                GameMove::Scissors,
+                GameMove::Lizard,
+                GameMove::Spock,
+            ],
+        }
+    }
+
+    pub fn allows(&self, game_move: &GameMove) -> bool {
+        self.moves().contains(game_move)
+    }
+
+    // Whether `game_move` wins against `other` under this rule set.
+    pub fn beats(&self, game_move: &GameMove, other: &GameMove) -> bool {
+        self.allows(game_move) && self.allows(other) && game_move.beaten_moves().contains(other)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -40,4 +625,7 @@ pub enum GameResult {
     HostWins,
     OpponentWins,
     Tie,
+    // The player did not move before the deadline, the other player wins.
+    HostForfeits,
+    OpponentForfeits,
 }